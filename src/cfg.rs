@@ -0,0 +1,144 @@
+//! Evaluation of `#[cfg(...)]` guards on `contents` items.
+//!
+//! The grammar parses `#[cfg(feature = "...")]` attributes into
+//! [`ContentsItem::Cfg`] nodes but leaves them in the tree. [`apply_cfg`]
+//! resolves each [`CfgPredicate`] against a set of enabled features, unwrapping
+//! the guarded item into its parent [`Contents`] when the predicate holds and
+//! dropping it otherwise, so a section can be laid out differently per build
+//! configuration.
+
+use {crate::*, std::collections::HashSet};
+
+/// Resolve every `#[cfg(...)]` guard in `items` against `enabled_features`.
+///
+/// Guarded items whose predicate holds are unwrapped in place; the rest are
+/// removed. The pass is idempotent — once applied, no `Cfg` nodes remain, so a
+/// second call is a no-op.
+pub fn apply_cfg(items: &mut [Item], enabled_features: &HashSet<String>) {
+    for item in items {
+        if let Item::Section(section) = item {
+            if let Some(contents) = &mut section.contents {
+                let resolved = filter_items(std::mem::take(&mut contents.items), enabled_features);
+                contents.items = resolved;
+            }
+        }
+    }
+}
+
+/// Resolve the `Cfg` guards in one `contents` item list.
+fn filter_items(items: Vec<ContentsItem>, enabled: &HashSet<String>) -> Vec<ContentsItem> {
+    let mut out = Vec::with_capacity(items.len());
+    'next: for mut item in items {
+        // Unwrap chains of held guards; a dropped guard skips the item.
+        while let ContentsItem::Cfg { predicate, item: inner } = item {
+            if !eval_predicate(&predicate, enabled) {
+                continue 'next;
+            }
+            item = *inner;
+        }
+        out.push(item);
+    }
+    out
+}
+
+/// Evaluate a single predicate against the enabled feature set.
+fn eval_predicate(predicate: &CfgPredicate, enabled: &HashSet<String>) -> bool {
+    match predicate {
+        CfgPredicate::Feature(name) => enabled.contains(name),
+        CfgPredicate::Not(inner) => !eval_predicate(inner, enabled),
+        CfgPredicate::All(preds) => preds.iter().all(|p| eval_predicate(p, enabled)),
+        CfgPredicate::Any(preds) => preds.iter().any(|p| eval_predicate(p, enabled)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    fn contents_of(items: &[Item]) -> &Contents {
+        match &items[0] {
+            Item::Section(s) => s.contents.as_ref().unwrap(),
+            other => panic!("expected section, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_enabled_guard_is_unwrapped() {
+        let src = r#"
+section .text {
+    contents {
+        input(.text*)
+        #[cfg(feature = "debug")]
+        input(.debug_text*)
+    }
+}
+"#
+        .trim();
+        let mut items = parse_file(src).unwrap();
+        apply_cfg(&mut items, &features(&["debug"]));
+        let contents = contents_of(&items);
+        assert_eq!(contents.items.len(), 2);
+        assert!(contents
+            .items
+            .iter()
+            .all(|i| !matches!(i, ContentsItem::Cfg { .. })));
+    }
+
+    #[test]
+    fn test_disabled_guard_is_dropped() {
+        let src = r#"
+section .text {
+    contents {
+        input(.text*)
+        #[cfg(feature = "debug")]
+        input(.debug_text*)
+    }
+}
+"#
+        .trim();
+        let mut items = parse_file(src).unwrap();
+        apply_cfg(&mut items, &features(&[]));
+        assert_eq!(contents_of(&items).items.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_all_any_not() {
+        let src = r#"
+section .text {
+    contents {
+        #[cfg(all(feature = "a", not(feature = "b")))]
+        input(.a*)
+        #[cfg(any(feature = "b", feature = "c"))]
+        input(.bc*)
+    }
+}
+"#
+        .trim();
+        let mut items = parse_file(src).unwrap();
+        apply_cfg(&mut items, &features(&["a", "c"]));
+        // `all(a, not(b))` holds (a on, b off); `any(b, c)` holds (c on).
+        assert_eq!(contents_of(&items).items.len(), 2);
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let src = r#"
+section .text {
+    contents {
+        #[cfg(feature = "debug")]
+        input(.debug_text*)
+    }
+}
+"#
+        .trim();
+        let mut items = parse_file(src).unwrap();
+        apply_cfg(&mut items, &features(&["debug"]));
+        let after_once = contents_of(&items).items.len();
+        apply_cfg(&mut items, &features(&["debug"]));
+        assert_eq!(contents_of(&items).items.len(), after_once);
+    }
+}
@@ -0,0 +1,307 @@
+//! Emission of a conventional GNU `ld` linker script from the linkrs AST.
+//!
+//! [`emit_gnu_ld`] translates the high-level DSL into the `MEMORY`, `PHDRS` and
+//! `SECTIONS` commands understood by real toolchains. Expressions are rendered
+//! textually (not evaluated) so the generated script stays symbolic.
+
+use {crate::*, std::fmt::Write};
+
+/// Render `items` as a GNU `ld` linker script.
+pub fn emit_gnu_ld(items: &[Item]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match item {
+            Item::MemoryMap(map) => emit_memory(map, &mut out),
+            Item::ElfSegments(segs) => emit_phdrs(segs, &mut out),
+            _ => {}
+        }
+    }
+    emit_sections(items, &mut out);
+    out
+}
+
+// ---------------------------------------------------------------------------
+// MEMORY
+// ---------------------------------------------------------------------------
+
+fn emit_memory(map: &MemoryMap, out: &mut String) {
+    out.push_str("MEMORY\n{\n");
+    for region in &map.regions {
+        let _ = writeln!(
+            out,
+            "    {} ({}) : ORIGIN = {}, LENGTH = {}",
+            region.name,
+            perm_letters(&region.permissions),
+            expr_to_ld(&region.start),
+            expr_to_ld(&region.size),
+        );
+    }
+    out.push_str("}\n\n");
+}
+
+fn perm_letters(perms: &Permissions) -> String {
+    let mut s = String::new();
+    if perms.read {
+        s.push('r');
+    }
+    if perms.write {
+        s.push('w');
+    }
+    if perms.execute {
+        s.push('x');
+    }
+    s
+}
+
+// ---------------------------------------------------------------------------
+// PHDRS
+// ---------------------------------------------------------------------------
+
+fn emit_phdrs(segs: &ElfSegments, out: &mut String) {
+    out.push_str("PHDRS\n{\n");
+    for seg in &segs.segments {
+        let _ = writeln!(
+            out,
+            "    {} {} FLAGS({});",
+            seg.name,
+            segment_type(&seg.segment_type),
+            perm_flags(&seg.permissions),
+        );
+    }
+    out.push_str("}\n\n");
+}
+
+fn segment_type(ty: &SegmentType) -> &'static str {
+    match ty {
+        SegmentType::Load => "PT_LOAD",
+        SegmentType::Dynamic => "PT_DYNAMIC",
+        SegmentType::Interp => "PT_INTERP",
+        SegmentType::Note => "PT_NOTE",
+        SegmentType::Phdr => "PT_PHDR",
+        SegmentType::Tls => "PT_TLS",
+        SegmentType::Null => "PT_NULL",
+    }
+}
+
+fn perm_flags(perms: &Permissions) -> u32 {
+    let mut flags = 0;
+    if perms.read {
+        flags |= 4;
+    }
+    if perms.write {
+        flags |= 2;
+    }
+    if perms.execute {
+        flags |= 1;
+    }
+    flags
+}
+
+// ---------------------------------------------------------------------------
+// SECTIONS
+// ---------------------------------------------------------------------------
+
+fn emit_sections(items: &[Item], out: &mut String) {
+    out.push_str("SECTIONS\n{\n");
+    for item in items {
+        match item {
+            Item::Section(section) => emit_section(section, out),
+            Item::Discard(discard) => emit_discard(discard, out),
+            Item::ProvideSymbols(provide) => emit_provide(provide, out),
+            _ => {}
+        }
+    }
+    out.push_str("}\n");
+}
+
+fn emit_section(section: &Section, out: &mut String) {
+    let address = section
+        .address
+        .as_ref()
+        .and_then(|a| a.start.as_ref())
+        .map(|e| format!(" {}", expr_to_ld(e)))
+        .unwrap_or_default();
+    let _ = writeln!(out, "    {}{} :", section.name, address);
+    out.push_str("    {\n");
+
+    let mut fill = None;
+    if let Some(contents) = &section.contents {
+        for item in &contents.items {
+            emit_contents_item(item, out, &mut fill);
+        }
+    }
+
+    out.push_str("    }");
+    if let Some(region) = &section.place_in {
+        let _ = write!(out, " > {region}");
+    }
+    if let Some(region) = &section.load_from {
+        let _ = write!(out, " AT> {region}");
+    }
+    if let Some(segment) = &section.output_to {
+        let _ = write!(out, " :{segment}");
+    }
+    if let Some(fill) = fill {
+        let _ = write!(out, " ={fill}");
+    }
+    out.push('\n');
+}
+
+fn emit_contents_item(item: &ContentsItem, out: &mut String, fill: &mut Option<String>) {
+    match item {
+        ContentsItem::Input(stmt) => {
+            let _ = writeln!(out, "        {}", input_spec(stmt));
+        }
+        ContentsItem::Keep(stmt) => {
+            let _ = writeln!(out, "        KEEP({})", input_spec(stmt));
+        }
+        ContentsItem::AlignTo(expr) => {
+            let _ = writeln!(out, "        . = ALIGN({});", expr_to_ld(expr));
+        }
+        ContentsItem::AdvanceBy(expr) => {
+            let _ = writeln!(out, "        . += {};", expr_to_ld(expr));
+        }
+        ContentsItem::FillPaddingWith(expr) => {
+            *fill = Some(expr_to_ld(expr));
+        }
+        ContentsItem::Symbol(sym) => {
+            if sym.public {
+                let _ = writeln!(out, "        PROVIDE({} = .);", sym.name);
+            } else {
+                let _ = writeln!(out, "        {} = .;", sym.name);
+            }
+        }
+        // A `#[cfg(...)]` guard that has not been stripped by `apply_cfg` is
+        // emitted as its inner item.
+        ContentsItem::Cfg { item, .. } => emit_contents_item(item, out, fill),
+    }
+}
+
+fn input_spec(stmt: &InputStmt) -> String {
+    let patterns = stmt.patterns.join(" ");
+    match &stmt.from {
+        Some(file) => format!("{file}({patterns})"),
+        None => format!("*({patterns})"),
+    }
+}
+
+fn emit_discard(discard: &Discard, out: &mut String) {
+    out.push_str("    /DISCARD/ :\n    {\n");
+    for stmt in &discard.patterns {
+        let _ = writeln!(out, "        {}", input_spec(stmt));
+    }
+    out.push_str("    }\n");
+}
+
+fn emit_provide(provide: &ProvideSymbols, out: &mut String) {
+    for (alias, target) in &provide.symbols {
+        let _ = writeln!(out, "    PROVIDE({alias} = {target});");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Expression rendering
+// ---------------------------------------------------------------------------
+
+fn expr_to_ld(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => n.to_string(),
+        Expr::Ident(s) => s.clone(),
+        Expr::Here => ".".to_owned(),
+        Expr::Size => "SIZEOF(.)".to_owned(),
+        Expr::UnaryMinus(inner) => format!("-{}", expr_to_ld(inner)),
+        Expr::BinOp { left, op, right } => {
+            format!("({} {} {})", expr_to_ld(left), binop_symbol(op), expr_to_ld(right))
+        }
+        Expr::Member { expr, field } => member_to_ld(expr, field),
+        Expr::Call { func, args } => {
+            let args = args.iter().map(expr_to_ld).collect::<Vec<_>>().join(", ");
+            format!("{}({})", expr_to_ld(func), args)
+        }
+    }
+}
+
+fn member_to_ld(expr: &Expr, field: &str) -> String {
+    if let Expr::Ident(region) = expr {
+        match field {
+            "start" => return format!("ORIGIN({region})"),
+            "size" => return format!("LENGTH({region})"),
+            "end" => return format!("(ORIGIN({region}) + LENGTH({region}))"),
+            _ => {}
+        }
+    }
+    format!("{}.{field}", expr_to_ld(expr))
+}
+
+fn binop_symbol(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "%",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_memory_and_section() {
+        let src = r#"
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+
+section .text {
+    place_in: FLASH,
+    output_to: segment(flash),
+
+    contents {
+        input(.text*)
+        keep(input(.vectors))
+    }
+}
+"#;
+        let script = emit_gnu_ld(&parse_file(src).unwrap());
+        assert!(script.contains("FLASH (rx) : ORIGIN = 134217728, LENGTH = 262144"));
+        assert!(script.contains(".text :"));
+        assert!(script.contains("*(.text*)"));
+        assert!(script.contains("KEEP(*(.vectors))"));
+        assert!(script.contains("} > FLASH :flash"));
+    }
+
+    #[test]
+    fn test_emit_phdrs_and_member() {
+        let src = r#"
+elf_segments {
+    segment flash {
+        type: Load,
+        permissions: Read | Execute,
+    }
+}
+
+const TOP: usize = FLASH.start + FLASH.size;
+
+section .stack {
+    address {
+        start: FLASH.start + FLASH.size,
+    }
+}
+"#;
+        let script = emit_gnu_ld(&parse_file(src).unwrap());
+        assert!(script.contains("flash PT_LOAD FLAGS(5);"));
+        assert!(script.contains(".stack (ORIGIN(FLASH) + LENGTH(FLASH)) :"));
+    }
+}
@@ -0,0 +1,298 @@
+//! Source-aware diagnostics built on `codespan-reporting`.
+//!
+//! [`analyze`] walks the lowered AST and collects every problem it can find in
+//! one pass — unknown region references, duplicate `const`/`region` names and
+//! overlapping memory regions — into a [`Diagnostics`] buffer. The buffer holds
+//! the original source so it can render each message with an underlined snippet
+//! pointing at the offending span, rather than surfacing a raw pest `Err`.
+
+use {
+    crate::{span::Span, *},
+    codespan_reporting::{
+        diagnostic::{Diagnostic, Label},
+        files::SimpleFile,
+        term::{
+            self,
+            termcolor::{Buffer, ColorChoice, StandardStream},
+        },
+    },
+    std::collections::HashMap,
+};
+
+/// Whether a diagnostic is an error or merely advisory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A collector that accumulates diagnostics against a single source file and
+/// renders them all at once.
+pub struct Diagnostics {
+    file: SimpleFile<String, String>,
+    entries: Vec<Diagnostic<()>>,
+    errors: usize,
+}
+
+impl Diagnostics {
+    /// Create an empty buffer holding `source` under the display name `name`.
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Diagnostics {
+            file: SimpleFile::new(name.into(), source.into()),
+            entries: Vec::new(),
+            errors: 0,
+        }
+    }
+
+    /// Record an error with one or more labelled spans.
+    pub fn error(&mut self, message: impl Into<String>, labels: Vec<(Span, String)>) {
+        self.push(Severity::Error, message.into(), labels);
+    }
+
+    /// Record a warning with one or more labelled spans.
+    pub fn warning(&mut self, message: impl Into<String>, labels: Vec<(Span, String)>) {
+        self.push(Severity::Warning, message.into(), labels);
+    }
+
+    fn push(&mut self, severity: Severity, message: String, labels: Vec<(Span, String)>) {
+        let diagnostic = match severity {
+            Severity::Error => {
+                self.errors += 1;
+                Diagnostic::error()
+            }
+            Severity::Warning => Diagnostic::warning(),
+        }
+        .with_message(message)
+        .with_labels(
+            labels
+                .into_iter()
+                .map(|(span, note)| Label::primary((), span.range()).with_message(note))
+                .collect(),
+        );
+        self.entries.push(diagnostic);
+    }
+
+    /// Number of diagnostics collected so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no diagnostics were collected.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether any collected diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// Render every diagnostic to a plain (uncolored) string.
+    pub fn render(&self) -> String {
+        let config = term::Config::default();
+        let mut buf = Buffer::no_color();
+        for entry in &self.entries {
+            term::emit(&mut buf, &config, &self.file, entry)
+                .expect("writing to an in-memory buffer cannot fail");
+        }
+        String::from_utf8(buf.into_inner()).expect("codespan-reporting emits valid UTF-8")
+    }
+
+    /// Emit every diagnostic to stderr, with color when the terminal allows it.
+    pub fn emit(&self) {
+        let config = term::Config::default();
+        let writer = StandardStream::stderr(ColorChoice::Auto);
+        let mut lock = writer.lock();
+        for entry in &self.entries {
+            let _ = term::emit(&mut lock, &config, &self.file, entry);
+        }
+    }
+}
+
+/// Analyze `items` against `source` and collect every diagnostic in one pass.
+///
+/// `name` is the source's display name (e.g. a file path) used in rendered
+/// snippets.
+pub fn analyze(name: impl Into<String>, source: impl Into<String>, items: &[Item]) -> Diagnostics {
+    let mut diags = Diagnostics::new(name, source);
+
+    // Duplicate `const` and `region` names, keyed to their first definition.
+    let mut consts: HashMap<&str, Span> = HashMap::new();
+    let mut regions: HashMap<&str, Span> = HashMap::new();
+    for item in items {
+        match item {
+            Item::Const(c) => {
+                if let Some(&first) = consts.get(c.name.as_str()) {
+                    diags.error(
+                        format!("duplicate const `{}`", c.name),
+                        vec![
+                            (c.span, "redefined here".to_owned()),
+                            (first, "first defined here".to_owned()),
+                        ],
+                    );
+                } else {
+                    consts.insert(&c.name, c.span);
+                }
+            }
+            Item::MemoryMap(map) => {
+                for region in &map.regions {
+                    if let Some(&first) = regions.get(region.name.as_str()) {
+                        diags.error(
+                            format!("duplicate region `{}`", region.name),
+                            vec![
+                                (region.span, "redefined here".to_owned()),
+                                (first, "first defined here".to_owned()),
+                            ],
+                        );
+                    } else {
+                        regions.insert(&region.name, region.span);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Unknown region references from section placement and address blocks.
+    for item in items {
+        if let Item::Section(section) = item {
+            if let Some(region) = &section.place_in {
+                if !regions.contains_key(region.as_str()) {
+                    diags.error(
+                        format!("undefined region `{region}`"),
+                        vec![(section.span, format!("`place_in: {region}` names no region"))],
+                    );
+                }
+            }
+            if let Some(address) = &section.address {
+                for region in [&address.region, &address.load_from_region].into_iter().flatten() {
+                    if !regions.contains_key(region.as_str()) {
+                        diags.error(
+                            format!("undefined region `{region}`"),
+                            vec![(address.span, format!("`{region}` names no region"))],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Overlapping memory regions, once start/size resolve to concrete values.
+    if let Ok(table) = evaluate(items) {
+        let mut laid_out: Vec<(&str, Span, u64, u64)> = regions
+            .iter()
+            .filter_map(|(&name, &span)| {
+                table.regions.get(name).map(|a| (name, span, a.start, a.size))
+            })
+            .collect();
+        laid_out.sort_by_key(|&(_, _, start, _)| start);
+        // A region can overlap any later-starting region, not just the next
+        // one, so compare every pair rather than only adjacent windows.
+        for (i, &(lo_name, lo_span, lo_start, lo_size)) in laid_out.iter().enumerate() {
+            let lo_end = lo_start.saturating_add(lo_size);
+            for &(hi_name, hi_span, hi_start, _) in &laid_out[i + 1..] {
+                if lo_end > hi_start {
+                    diags.error(
+                        format!("memory regions `{lo_name}` and `{hi_name}` overlap"),
+                        vec![
+                            (lo_span, format!("`{lo_name}` ends at {lo_end:#x}")),
+                            (hi_span, format!("`{hi_name}` starts at {hi_start:#x}")),
+                        ],
+                    );
+                }
+            }
+        }
+    }
+
+    diags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_region_is_reported() {
+        let src = r#"
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+    region FLASH {
+        permissions: Read,
+        start: 0x0900_0000,
+        size: 4K,
+    }
+}
+"#
+        .trim();
+        let items = parse_file(src).unwrap();
+        let diags = analyze("test.lrs", src, &items);
+        assert!(diags.has_errors());
+        assert!(diags.render().contains("duplicate region `FLASH`"));
+    }
+
+    #[test]
+    fn test_undefined_region_reference() {
+        let src = r#"
+section .text {
+    place_in: FLSH,
+    contents {
+        input(.text*)
+    }
+}
+"#
+        .trim();
+        let items = parse_file(src).unwrap();
+        let diags = analyze("test.lrs", src, &items);
+        assert!(diags.render().contains("undefined region `FLSH`"));
+    }
+
+    #[test]
+    fn test_overlapping_regions() {
+        let src = r#"
+memory_map {
+    region A {
+        permissions: Read,
+        start: 0x1000,
+        size: 0x1000,
+    }
+    region B {
+        permissions: Read,
+        start: 0x1800,
+        size: 0x1000,
+    }
+}
+"#
+        .trim();
+        let items = parse_file(src).unwrap();
+        let diags = analyze("test.lrs", src, &items);
+        assert!(diags.render().contains("overlap"));
+    }
+
+    #[test]
+    fn test_clean_input_is_silent() {
+        let src = r#"
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+
+section .text {
+    place_in: FLASH,
+    contents {
+        input(.text*)
+    }
+}
+"#
+        .trim();
+        let items = parse_file(src).unwrap();
+        let diags = analyze("test.lrs", src, &items);
+        assert!(diags.is_empty(), "unexpected: {}", diags.render());
+    }
+}
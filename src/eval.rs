@@ -0,0 +1,477 @@
+//! Constant/expression evaluation with symbol resolution.
+//!
+//! [`evaluate`] takes the lowered AST and resolves every [`Expr`] it can to a
+//! concrete `u64`. It builds a symbol table from `const` declarations, memory
+//! regions and `symbol` definitions, then folds `BinOp`/`UnaryMinus`/`Member`/
+//! `Call` nodes against it. Section assertions (`assert(size() < 64K, ...)`)
+//! are checked as the sections are laid out in declaration order.
+
+use {
+    crate::*,
+    std::{
+        collections::{HashMap, HashSet},
+        fmt,
+    },
+};
+
+/// Error raised while resolving or evaluating an expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum EvalError {
+    /// Reference to an identifier with no binding.
+    Undefined(String),
+    /// A `const` participates in a definition cycle.
+    Cycle(String),
+    /// `REGION.field` named an attribute that does not exist.
+    UnknownField { region: String, field: String },
+    /// The left-hand side of a `.field` access was not a region.
+    NotARegion(String),
+    /// Division or modulo by zero.
+    DivByZero,
+    /// `here()`/`size()` used where no section is being laid out.
+    NoLayoutContext,
+    /// A construct that the evaluator cannot reduce to a number.
+    Unsupported(String),
+    /// A section `assert(...)` evaluated to false.
+    AssertionFailed(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Undefined(n) => write!(f, "undefined identifier `{n}`"),
+            EvalError::Cycle(n) => write!(f, "cyclic constant definition involving `{n}`"),
+            EvalError::UnknownField { region, field } => {
+                write!(f, "region `{region}` has no attribute `{field}`")
+            }
+            EvalError::NotARegion(n) => write!(f, "`{n}` is not a region"),
+            EvalError::DivByZero => write!(f, "division by zero"),
+            EvalError::NoLayoutContext => {
+                write!(f, "`here()`/`size()` used outside a section")
+            }
+            EvalError::Unsupported(m) => write!(f, "cannot evaluate: {m}"),
+            EvalError::AssertionFailed(m) => write!(f, "assertion failed: {m}"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Resolved attributes of a memory region.
+#[derive(Debug, Clone, Copy)]
+pub struct RegionAttrs {
+    pub start: u64,
+    pub size: u64,
+}
+
+/// Fully resolved symbol table produced by [`evaluate`].
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    pub consts: HashMap<String, u64>,
+    pub regions: HashMap<String, RegionAttrs>,
+    pub symbols: HashMap<String, u64>,
+}
+
+/// Resolve all constants and regions and check every section assertion.
+pub fn evaluate(items: &[Item]) -> Result<SymbolTable, EvalError> {
+    let mut resolver = Resolver::collect(items);
+    resolver.resolve_all()?;
+    let mut table = SymbolTable {
+        consts: resolver.const_values.clone(),
+        regions: resolver.region_values.clone(),
+        symbols: HashMap::new(),
+    };
+    // Lay sections out in order so `here()`/`size()` and symbols resolve.
+    for item in items {
+        if let Item::Section(section) = item {
+            layout_section(section, &mut resolver, &mut table)?;
+        }
+    }
+    Ok(table)
+}
+
+/// Evaluate a single expression against an already-built table, with no
+/// layout context (so `here()`/`size()` are errors).
+pub fn eval_const(expr: &Expr, table: &SymbolTable) -> Result<u64, EvalError> {
+    let mut resolver = Resolver {
+        const_exprs: HashMap::new(),
+        region_exprs: HashMap::new(),
+        const_values: table.consts.clone(),
+        region_values: table.regions.clone(),
+        visiting: HashSet::new(),
+    };
+    eval_expr(expr, &mut resolver, None)
+}
+
+// ---------------------------------------------------------------------------
+// Resolver
+// ---------------------------------------------------------------------------
+
+struct Resolver {
+    const_exprs: HashMap<String, Expr>,
+    region_exprs: HashMap<String, (Expr, Expr)>, // name -> (start, size)
+    const_values: HashMap<String, u64>,
+    region_values: HashMap<String, RegionAttrs>,
+    visiting: HashSet<String>,
+}
+
+impl Resolver {
+    fn collect(items: &[Item]) -> Self {
+        let mut const_exprs = HashMap::new();
+        let mut region_exprs = HashMap::new();
+        for item in items {
+            match item {
+                Item::Const(c) => {
+                    const_exprs.insert(c.name.clone(), c.value.clone());
+                }
+                Item::MemoryMap(map) => {
+                    for r in &map.regions {
+                        region_exprs
+                            .insert(r.name.clone(), (r.start.clone(), r.size.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Resolver {
+            const_exprs,
+            region_exprs,
+            const_values: HashMap::new(),
+            region_values: HashMap::new(),
+            visiting: HashSet::new(),
+        }
+    }
+
+    fn resolve_all(&mut self) -> Result<(), EvalError> {
+        let names: Vec<String> = self.region_exprs.keys().cloned().collect();
+        for name in names {
+            self.region(&name)?;
+        }
+        let names: Vec<String> = self.const_exprs.keys().cloned().collect();
+        for name in names {
+            self.constant(&name)?;
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self, name: &str) -> Result<u64, EvalError> {
+        if let Some(v) = self.const_values.get(name) {
+            return Ok(*v);
+        }
+        let expr = self
+            .const_exprs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EvalError::Undefined(name.to_owned()))?;
+        // Guard against reference cycles. Constants and regions share one
+        // `visiting` set but live in separate namespaces, so tag the key to
+        // avoid a false cycle between a const and a region of the same name.
+        let key = format!("const {name}");
+        if !self.visiting.insert(key.clone()) {
+            return Err(EvalError::Cycle(name.to_owned()));
+        }
+        let result = eval_expr(&expr, self, None);
+        self.visiting.remove(&key);
+        let value = result?;
+        self.const_values.insert(name.to_owned(), value);
+        Ok(value)
+    }
+
+    fn region(&mut self, name: &str) -> Result<RegionAttrs, EvalError> {
+        if let Some(attrs) = self.region_values.get(name) {
+            return Ok(*attrs);
+        }
+        let (start_e, size_e) = self
+            .region_exprs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EvalError::NotARegion(name.to_owned()))?;
+        let key = format!("region {name}");
+        if !self.visiting.insert(key.clone()) {
+            return Err(EvalError::Cycle(name.to_owned()));
+        }
+        let result = eval_expr(&start_e, self, None)
+            .and_then(|start| eval_expr(&size_e, self, None).map(|size| (start, size)));
+        self.visiting.remove(&key);
+        let (start, size) = result?;
+        let attrs = RegionAttrs { start, size };
+        self.region_values.insert(name.to_owned(), attrs);
+        Ok(attrs)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layout
+// ---------------------------------------------------------------------------
+
+/// Mutable state while laying out a single section.
+struct Layout {
+    /// Current location counter.
+    here: u64,
+    /// Location counter at the start of the section.
+    base: u64,
+}
+
+fn layout_section(
+    section: &Section,
+    resolver: &mut Resolver,
+    table: &mut SymbolTable,
+) -> Result<(), EvalError> {
+    // Start from an explicit address if one was given, otherwise the region
+    // origin, otherwise zero.
+    let start = match section.address.as_ref().and_then(|a| a.start.as_ref()) {
+        Some(expr) => eval_expr(expr, resolver, None)?,
+        None => match &section.place_in {
+            Some(region) => resolver
+                .region_values
+                .get(region)
+                .map(|a| a.start)
+                .ok_or_else(|| EvalError::NotARegion(region.clone()))?,
+            None => 0,
+        },
+    };
+    let mut layout = Layout { here: start, base: start };
+
+    if let Some(contents) = &section.contents {
+        for item in &contents.items {
+            apply_contents_item(item, resolver, &mut layout, table)?;
+        }
+    }
+
+    for assertion in &section.assertions {
+        let value = eval_expr(&assertion.condition, resolver, Some(&layout))?;
+        if value == 0 {
+            return Err(EvalError::AssertionFailed(assertion.message.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn apply_contents_item(
+    item: &ContentsItem,
+    resolver: &mut Resolver,
+    layout: &mut Layout,
+    table: &mut SymbolTable,
+) -> Result<(), EvalError> {
+    match item {
+        ContentsItem::AlignTo(expr) => {
+            let align = eval_expr(expr, resolver, Some(layout))?;
+            if align != 0 {
+                layout.here = layout.here.div_ceil(align) * align;
+            }
+        }
+        ContentsItem::AdvanceBy(expr) => {
+            layout.here += eval_expr(expr, resolver, Some(layout))?;
+        }
+        ContentsItem::Symbol(sym) => {
+            table.symbols.insert(sym.name.clone(), layout.here);
+        }
+        ContentsItem::Cfg { item, .. } => {
+            apply_contents_item(item, resolver, layout, table)?
+        }
+        // Input globs and fill patterns do not contribute a statically known
+        // size at analysis time.
+        ContentsItem::Input(_)
+        | ContentsItem::Keep(_)
+        | ContentsItem::FillPaddingWith(_) => {}
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Expression folding
+// ---------------------------------------------------------------------------
+
+fn eval_expr(
+    expr: &Expr,
+    resolver: &mut Resolver,
+    layout: Option<&Layout>,
+) -> Result<u64, EvalError> {
+    match expr {
+        Expr::Number(n) => Ok(*n),
+        Expr::Here => layout.map(|l| l.here).ok_or(EvalError::NoLayoutContext),
+        Expr::Size => layout.map(|l| l.here - l.base).ok_or(EvalError::NoLayoutContext),
+        // Resolve identifiers lazily through the resolver so a const may refer
+        // to one declared later; `Resolver::constant` guards against cycles.
+        Expr::Ident(name) => {
+            if let Some(v) = resolver.const_values.get(name).copied() {
+                Ok(v)
+            } else if resolver.const_exprs.contains_key(name.as_str()) {
+                resolver.constant(name)
+            } else if let Some(attrs) = resolver.region_values.get(name).copied() {
+                Ok(attrs.start)
+            } else if resolver.region_exprs.contains_key(name.as_str()) {
+                Ok(resolver.region(name)?.start)
+            } else {
+                Err(EvalError::Undefined(name.clone()))
+            }
+        }
+        Expr::UnaryMinus(inner) => {
+            Ok(0u64.wrapping_sub(eval_expr(inner, resolver, layout)?))
+        }
+        Expr::BinOp { left, op, right } => {
+            let l = eval_expr(left, resolver, layout)?;
+            let r = eval_expr(right, resolver, layout)?;
+            Ok(apply_binop(op, l, r)?)
+        }
+        Expr::Member { expr, field } => eval_member(expr, field, resolver),
+        Expr::Call { func, .. } => Err(EvalError::Unsupported(format!(
+            "call of {func:?} is not a constant expression"
+        ))),
+    }
+}
+
+fn eval_member(expr: &Expr, field: &str, resolver: &mut Resolver) -> Result<u64, EvalError> {
+    let region = match expr {
+        Expr::Ident(name) => name,
+        other => return Err(EvalError::Unsupported(format!("member access on {other:?}"))),
+    };
+    let attrs = match resolver.region_values.get(region).copied() {
+        Some(attrs) => attrs,
+        None if resolver.region_exprs.contains_key(region.as_str()) => {
+            resolver.region(region)?
+        }
+        None => return Err(EvalError::NotARegion(region.clone())),
+    };
+    match field {
+        "start" => Ok(attrs.start),
+        "size" => Ok(attrs.size),
+        "end" => Ok(attrs.start.wrapping_add(attrs.size)),
+        other => Err(EvalError::UnknownField {
+            region: region.clone(),
+            field: other.to_owned(),
+        }),
+    }
+}
+
+fn apply_binop(op: &BinOp, l: u64, r: u64) -> Result<u64, EvalError> {
+    Ok(match op {
+        BinOp::Add => l.wrapping_add(r),
+        BinOp::Sub => l.wrapping_sub(r),
+        BinOp::Mul => l.wrapping_mul(r),
+        BinOp::Div => l.checked_div(r).ok_or(EvalError::DivByZero)?,
+        BinOp::Mod => l.checked_rem(r).ok_or(EvalError::DivByZero)?,
+        BinOp::Lt => (l < r) as u64,
+        BinOp::Gt => (l > r) as u64,
+        BinOp::Le => (l <= r) as u64,
+        BinOp::Ge => (l >= r) as u64,
+        BinOp::Eq => (l == r) as u64,
+        BinOp::Ne => (l != r) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(src: &str) -> SymbolTable {
+        evaluate(&parse_file(src).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_const_and_region_members() {
+        let src = r#"
+const BASE: usize = 0x1000;
+pub const LIMIT: usize = BASE + 4K;
+
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+"#;
+        let t = table(src);
+        assert_eq!(t.consts["BASE"], 0x1000);
+        assert_eq!(t.consts["LIMIT"], 0x1000 + 4 * 1024);
+        assert_eq!(t.regions["FLASH"].start, 0x0800_0000);
+        assert_eq!(t.regions["FLASH"].size, 256 * 1024);
+    }
+
+    #[test]
+    fn test_forward_reference_resolves() {
+        // `LIMIT` refers to `BASE`, which is declared after it.
+        let src = "const LIMIT: usize = BASE + 1;\nconst BASE: usize = 5;";
+        let t = table(src);
+        assert_eq!(t.consts["BASE"], 5);
+        assert_eq!(t.consts["LIMIT"], 6);
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let src = "const A: usize = B;\nconst B: usize = A;";
+        let err = evaluate(&parse_file(src).unwrap()).unwrap_err();
+        assert!(matches!(err, EvalError::Cycle(_)));
+    }
+
+    #[test]
+    fn test_region_cycle_detection() {
+        let src = r#"
+memory_map {
+    region A {
+        permissions: Read,
+        start: B.start,
+        size: 1,
+    }
+    region B {
+        permissions: Read,
+        start: A.start,
+        size: 1,
+    }
+}
+"#;
+        let err = evaluate(&parse_file(src).unwrap()).unwrap_err();
+        assert!(matches!(err, EvalError::Cycle(_)));
+    }
+
+    #[test]
+    fn test_const_referencing_same_named_region_is_not_a_cycle() {
+        // A const and a region may share a name; the const reading the region's
+        // members is not a cycle and must resolve cleanly.
+        let src = r#"
+const FLASH: usize = FLASH.start + FLASH.size;
+
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+"#;
+        let t = table(src);
+        assert_eq!(t.consts["FLASH"], 0x0800_0000 + 256 * 1024);
+    }
+
+    #[test]
+    fn test_undefined_identifier() {
+        let src = "const A: usize = MISSING;";
+        let err = evaluate(&parse_file(src).unwrap()).unwrap_err();
+        assert_eq!(err, EvalError::Undefined("MISSING".into()));
+    }
+
+    #[test]
+    fn test_assertion_checked_against_layout() {
+        let src = r#"
+section .text {
+    contents {
+        advance_by(16);
+        align_to(8);
+    }
+    assert(size() < 64K, "text too large");
+}
+"#;
+        assert!(evaluate(&parse_file(src).unwrap()).is_ok());
+
+        let src_bad = r#"
+section .text {
+    contents {
+        advance_by(128K);
+    }
+    assert(size() < 64K, "text too large");
+}
+"#;
+        let err = evaluate(&parse_file(src_bad).unwrap()).unwrap_err();
+        assert_eq!(err, EvalError::AssertionFailed("text too large".into()));
+    }
+}
@@ -0,0 +1,473 @@
+//! Canonical pretty-printer for the linkrs DSL.
+//!
+//! [`format`] re-emits a lowered AST in a single canonical style — four-space
+//! indentation, attributes in a fixed order, numbers normalized to decimal
+//! and expressions fully parenthesized — so that parsing, formatting and
+//! re-parsing is a fixed point. It is to linker scripts what `rustfmt` is to
+//! Rust, and the round-trip property tests below guard the lowering and
+//! printing code against drift as the grammar grows.
+
+use {crate::*, std::fmt::Write};
+
+/// Re-emit `items` as a canonically formatted linkrs source string.
+pub fn format(items: &[Item]) -> String {
+    let mut out = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        fmt_item(item, &mut out);
+    }
+    out
+}
+
+fn fmt_item(item: &Item, out: &mut String) {
+    match item {
+        Item::Const(c) => fmt_const(c, out),
+        Item::MemoryMap(m) => fmt_memory_map(m, out),
+        Item::ElfSegments(s) => fmt_elf_segments(s, out),
+        Item::Section(s) => fmt_section(s, out),
+        Item::Discard(d) => fmt_discard(d, out),
+        Item::ProvideSymbols(p) => fmt_provide(p, out),
+    }
+}
+
+fn pad(level: usize) -> String {
+    "    ".repeat(level)
+}
+
+/// Wrap a string literal the way the grammar reads it back: the `string` rule
+/// performs no escape processing, so the content is emitted verbatim between
+/// quotes rather than with Rust's `{:?}` escaping.
+fn quote(s: &str) -> String {
+    format!("\"{s}\"")
+}
+
+// ---------------------------------------------------------------------------
+// Constants
+// ---------------------------------------------------------------------------
+
+fn fmt_const(c: &ConstDecl, out: &mut String) {
+    if c.public {
+        out.push_str("pub ");
+    }
+    out.push_str("const ");
+    out.push_str(&c.name);
+    if let Some(ty) = &c.type_ann {
+        let _ = write!(out, ": {ty}");
+    }
+    let _ = writeln!(out, " = {};", fmt_expr(&c.value));
+}
+
+// ---------------------------------------------------------------------------
+// Memory map
+// ---------------------------------------------------------------------------
+
+fn fmt_memory_map(map: &MemoryMap, out: &mut String) {
+    out.push_str("memory_map {\n");
+    for (i, region) in map.regions.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}region {} {{", pad(1), region.name);
+        let _ = writeln!(out, "{}permissions: {},", pad(2), fmt_permissions(&region.permissions));
+        let _ = writeln!(out, "{}start: {},", pad(2), fmt_expr(&region.start));
+        let _ = writeln!(out, "{}size: {},", pad(2), fmt_expr(&region.size));
+        let _ = writeln!(out, "{}}}", pad(1));
+    }
+    out.push_str("}\n");
+}
+
+fn fmt_permissions(perms: &Permissions) -> String {
+    let mut parts = Vec::new();
+    if perms.read {
+        parts.push("Read");
+    }
+    if perms.write {
+        parts.push("Write");
+    }
+    if perms.execute {
+        parts.push("Execute");
+    }
+    parts.join(" | ")
+}
+
+// ---------------------------------------------------------------------------
+// ELF segments
+// ---------------------------------------------------------------------------
+
+fn fmt_elf_segments(segs: &ElfSegments, out: &mut String) {
+    out.push_str("elf_segments {\n");
+    for (i, seg) in segs.segments.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}segment {} {{", pad(1), seg.name);
+        let _ = writeln!(out, "{}type: {},", pad(2), segment_type(&seg.segment_type));
+        let _ = writeln!(out, "{}permissions: {},", pad(2), fmt_permissions(&seg.permissions));
+        let _ = writeln!(out, "{}}}", pad(1));
+    }
+    out.push_str("}\n");
+}
+
+fn segment_type(ty: &SegmentType) -> &'static str {
+    match ty {
+        SegmentType::Load => "Load",
+        SegmentType::Dynamic => "Dynamic",
+        SegmentType::Interp => "Interp",
+        SegmentType::Note => "Note",
+        SegmentType::Phdr => "Phdr",
+        SegmentType::Tls => "Tls",
+        SegmentType::Null => "Null",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Sections
+// ---------------------------------------------------------------------------
+
+fn fmt_section(section: &Section, out: &mut String) {
+    let _ = writeln!(out, "section {} {{", section.name);
+    if let Some(region) = &section.place_in {
+        let _ = writeln!(out, "{}place_in: {region},", pad(1));
+    }
+    if let Some(region) = &section.load_from {
+        let _ = writeln!(out, "{}load_from: {region},", pad(1));
+    }
+    if let Some(segment) = &section.output_to {
+        let _ = writeln!(out, "{}output_to: segment({segment}),", pad(1));
+    }
+    if let Some(perms) = &section.permissions {
+        let _ = writeln!(out, "{}permissions: {},", pad(1), fmt_permissions(perms));
+    }
+    if let Some(occupies) = section.occupies_file_space {
+        let _ = writeln!(out, "{}occupies_file_space: {occupies},", pad(1));
+    }
+    if let Some(address) = &section.address {
+        fmt_address_block(address, out);
+    }
+    if let Some(position) = &section.file_position {
+        fmt_file_position(position, out);
+    }
+    if let Some(contents) = &section.contents {
+        fmt_contents(contents, out);
+    }
+    for assertion in &section.assertions {
+        let _ = writeln!(
+            out,
+            "{}assert({}, {});",
+            pad(1),
+            fmt_expr(&assertion.condition),
+            quote(&assertion.message),
+        );
+    }
+    if !section.no_cross_refs.is_empty() {
+        let _ = writeln!(
+            out,
+            "{}assert_no_cross_references_to({});",
+            pad(1),
+            section.no_cross_refs.join(", "),
+        );
+    }
+    out.push_str("}\n");
+}
+
+fn fmt_address_block(block: &AddressBlock, out: &mut String) {
+    let mut fields = Vec::new();
+    if let Some(e) = &block.start {
+        fields.push(format!("start: {}", fmt_expr(e)));
+    }
+    if let Some(e) = &block.size {
+        fields.push(format!("size: {}", fmt_expr(e)));
+    }
+    if let Some(e) = &block.alignment {
+        fields.push(format!("alignment: {}", fmt_expr(e)));
+    }
+    if let Some(name) = &block.follows {
+        fields.push(format!("follows: {name}"));
+    }
+    if let Some(e) = &block.virtual_base {
+        fields.push(format!("virtual_base: {}", fmt_expr(e)));
+    }
+    if let Some(name) = &block.region {
+        fields.push(format!("region: {name}"));
+    }
+    if let Some(name) = &block.load_from_region {
+        fields.push(format!("load_from_region: {name}"));
+    }
+    let _ = writeln!(out, "{}address {{", pad(1));
+    for field in fields {
+        let _ = writeln!(out, "{}{field},", pad(2));
+    }
+    let _ = writeln!(out, "{}}}", pad(1));
+}
+
+fn fmt_file_position(position: &FilePosition, out: &mut String) {
+    let start = match &position.start {
+        FilePositionStart::Origin => "Origin".to_owned(),
+        FilePositionStart::Expr(e) => fmt_expr(e),
+    };
+    let _ = writeln!(out, "{}file_position {{", pad(1));
+    let _ = writeln!(out, "{}start: {start},", pad(2));
+    let _ = writeln!(out, "{}}}", pad(1));
+}
+
+fn fmt_contents(contents: &Contents, out: &mut String) {
+    let _ = writeln!(out, "{}contents {{", pad(1));
+    for item in &contents.items {
+        fmt_contents_item(item, 2, out);
+    }
+    let _ = writeln!(out, "{}}}", pad(1));
+}
+
+fn fmt_contents_item(item: &ContentsItem, level: usize, out: &mut String) {
+    match item {
+        ContentsItem::Symbol(sym) => {
+            if sym.public {
+                let _ = write!(out, "{}pub ", pad(level));
+            } else {
+                out.push_str(&pad(level));
+            }
+            let _ = writeln!(out, "symbol {} = {};", sym.name, fmt_location(&sym.value));
+        }
+        ContentsItem::Input(stmt) => {
+            let _ = writeln!(out, "{}{};", pad(level), fmt_input(stmt));
+        }
+        ContentsItem::Keep(stmt) => {
+            let _ = writeln!(out, "{}keep({});", pad(level), fmt_input(stmt));
+        }
+        ContentsItem::AlignTo(e) => {
+            let _ = writeln!(out, "{}align_to({});", pad(level), fmt_expr(e));
+        }
+        ContentsItem::AdvanceBy(e) => {
+            let _ = writeln!(out, "{}advance_by({});", pad(level), fmt_expr(e));
+        }
+        ContentsItem::FillPaddingWith(e) => {
+            let _ = writeln!(out, "{}fill_padding_with({});", pad(level), fmt_expr(e));
+        }
+        ContentsItem::Cfg { predicate, item } => {
+            let _ = writeln!(out, "{}#[cfg({})]", pad(level), fmt_cfg_predicate(predicate));
+            fmt_contents_item(item, level, out);
+        }
+    }
+}
+
+fn fmt_location(expr: &LocationExpr) -> String {
+    match &expr.accessor {
+        Some(LocationAccessor::Physical) => "here().physical".to_owned(),
+        Some(LocationAccessor::Virtual) => "here().virtual".to_owned(),
+        None => "here()".to_owned(),
+    }
+}
+
+fn fmt_input(stmt: &InputStmt) -> String {
+    let mut parts = stmt.patterns.clone();
+    if let Some(sort) = &stmt.sort_by {
+        parts.push(format!("sort_by: {}", sort_key(sort)));
+    }
+    format!("input({})", parts.join(", "))
+}
+
+fn sort_key(key: &SortKey) -> &'static str {
+    match key {
+        SortKey::Name => "Name",
+        SortKey::Address => "Address",
+        SortKey::Alignment => "Alignment",
+    }
+}
+
+fn fmt_cfg_predicate(predicate: &CfgPredicate) -> String {
+    match predicate {
+        CfgPredicate::Feature(name) => format!("feature = {}", quote(name)),
+        CfgPredicate::Not(inner) => format!("not({})", fmt_cfg_predicate(inner)),
+        CfgPredicate::All(preds) => format!("all({})", join_predicates(preds)),
+        CfgPredicate::Any(preds) => format!("any({})", join_predicates(preds)),
+    }
+}
+
+fn join_predicates(preds: &[CfgPredicate]) -> String {
+    preds.iter().map(fmt_cfg_predicate).collect::<Vec<_>>().join(", ")
+}
+
+// ---------------------------------------------------------------------------
+// Discard & provide_symbols
+// ---------------------------------------------------------------------------
+
+fn fmt_discard(discard: &Discard, out: &mut String) {
+    out.push_str("discard {\n");
+    for stmt in &discard.patterns {
+        let _ = writeln!(out, "{}{};", pad(1), fmt_input(stmt));
+    }
+    out.push_str("}\n");
+}
+
+fn fmt_provide(provide: &ProvideSymbols, out: &mut String) {
+    out.push_str("provide_symbols {\n");
+    for (alias, target) in &provide.symbols {
+        let _ = writeln!(out, "{}{alias} = {target},", pad(1));
+    }
+    out.push_str("}\n");
+}
+
+// ---------------------------------------------------------------------------
+// Expressions
+// ---------------------------------------------------------------------------
+
+/// Render an expression with full parenthesization so precedence round-trips
+/// regardless of how the source was originally written.
+fn fmt_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => n.to_string(),
+        Expr::Ident(name) => name.clone(),
+        Expr::Here => "here()".to_owned(),
+        Expr::Size => "size()".to_owned(),
+        Expr::UnaryMinus(inner) => format!("-{}", fmt_expr(inner)),
+        Expr::BinOp { left, op, right } => {
+            format!("({} {} {})", fmt_expr(left), binop_symbol(op), fmt_expr(right))
+        }
+        Expr::Member { expr, field } => format!("{}.{field}", fmt_expr(expr)),
+        Expr::Call { func, args } => {
+            let args = args.iter().map(fmt_expr).collect::<Vec<_>>().join(", ");
+            format!("{}({args})", fmt_expr(func))
+        }
+    }
+}
+
+fn binop_symbol(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "%",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `src` and re-encode it as span-stripped JSON. The byte offsets in
+    /// each span legitimately shift when the text is re-laid-out, so they are
+    /// removed before comparison; everything else must match.
+    #[cfg(feature = "serde")]
+    fn ast_without_spans(src: &str) -> serde_json::Value {
+        fn strip(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    map.remove("span");
+                    for child in map.values_mut() {
+                        strip(child);
+                    }
+                }
+                serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+        let mut value = serde_json::from_str(&to_json(&parse_file(src).unwrap())).unwrap();
+        strip(&mut value);
+        value
+    }
+
+    /// Formatting round-trips. At the text level it is a fixed point: reparsing
+    /// canonical output and formatting again yields identical text. When the
+    /// `serde` feature is on we additionally assert the stronger property that
+    /// `parse(format(parse(src)))` is the *same AST* as `parse(src)` — the AST
+    /// types don't implement `PartialEq`, so we compare their span-stripped JSON
+    /// serialization, which catches a field the formatter silently dropped or
+    /// reordered even when the text still happens to be a fixed point.
+    fn assert_round_trips(src: &str) {
+        let once = format(&parse_file(src).unwrap());
+        let twice = format(&parse_file(&once).unwrap());
+        assert_eq!(once, twice, "formatting was not a fixed point\n---\n{once}");
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            ast_without_spans(&once),
+            ast_without_spans(src),
+            "format changed the AST\n---\n{once}",
+        );
+    }
+
+    #[test]
+    fn test_round_trip_full_embedded() {
+        assert_round_trips(
+            r#"
+const PAGE: usize = 4K;
+
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+
+    region RAM {
+        permissions: Read | Write | Execute,
+        start: 0x2000_0000,
+        size: 64K,
+    }
+}
+
+elf_segments {
+    segment flash {
+        type: Load,
+        permissions: Read | Execute,
+    }
+}
+
+section .text {
+    place_in: FLASH,
+    output_to: segment(flash),
+
+    contents {
+        input(.text*)
+        keep(input(.vectors))
+        align_to(2048);
+        pub symbol __VECTORS = here();
+    }
+
+    assert(size() < 64K, "text too large");
+}
+
+discard {
+    input(.comment)
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_round_trip_cfg_and_exprs() {
+        assert_round_trips(
+            r#"
+const TOP: Address = FLASH.start + FLASH.size * 2;
+
+section .data {
+    contents {
+        #[cfg(all(feature = "a", not(feature = "b")))]
+        input(.data*)
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_round_trip_message_with_backslash() {
+        // The grammar does no escape processing, so a backslash in the message
+        // must survive verbatim through format -> parse -> format.
+        assert_round_trips(r#"section .x { assert(size() < 1K, "path\to\thing"); }"#);
+    }
+
+    #[test]
+    fn test_number_normalized_to_decimal() {
+        let items = parse_file("const N: usize = 0x10;").unwrap();
+        assert_eq!(format(&items), "const N: usize = 16;\n");
+    }
+}
@@ -0,0 +1,43 @@
+//! JSON intermediate representation of the parsed AST.
+//!
+//! With the `serde` feature enabled every AST type derives `Serialize`/
+//! `Deserialize`, so [`to_json`] can emit a stable JSON document that external
+//! tooling — editors, layout diffs, or a separate ELF backend — can consume
+//! without linking against this crate.
+
+use crate::Item;
+
+/// Serialize a lowered item list to a pretty-printed JSON string.
+pub fn to_json(items: &[Item]) -> String {
+    serde_json::to_string_pretty(items).expect("AST types serialize infallibly")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_file, to_json, Item};
+
+    #[test]
+    fn test_roundtrip_through_json() {
+        let src = r#"
+const PAGE: usize = 4K;
+
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+"#
+        .trim();
+        let items = parse_file(src).unwrap();
+        let json = to_json(&items);
+        let back: Vec<Item> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), items.len());
+        // The second item is the memory map with its single region preserved.
+        match &back[1] {
+            Item::MemoryMap(m) => assert_eq!(m.regions[0].name, "FLASH"),
+            other => panic!("expected memory_map, got {other:?}"),
+        }
+    }
+}
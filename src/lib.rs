@@ -7,19 +7,45 @@ use pest_derive::Parser;
 #[grammar = "linkrs.pest"]
 pub struct LinkrsParser;
 
+mod cfg;
+mod codegen;
+mod diagnostics;
+mod eval;
+mod fmt;
+#[cfg(feature = "serde")]
+mod json;
+mod lower;
+mod span;
+
+pub use cfg::apply_cfg;
+pub use codegen::emit_gnu_ld;
+pub use diagnostics::{analyze, Diagnostics, Severity};
+pub use eval::{eval_const, evaluate, EvalError, RegionAttrs, SymbolTable};
+pub use fmt::format;
+#[cfg(feature = "serde")]
+pub use json::to_json;
+pub use lower::{parse_expr, parse_file, ParseError};
+pub use span::Span;
+
 // Prototype AST types
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Item {
     Const(ConstDecl),
     MemoryMap(MemoryMap),
     ElfSegments(ElfSegments),
-    Section(Section),
+    // Boxed: `Section` is far larger than the other variants, so keeping it
+    // inline would bloat every `Item` (clippy `large_enum_variant`).
+    Section(Box<Section>),
     Discard(Discard),
     ProvideSymbols(ProvideSymbols),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstDecl {
+    pub span: Span,
+
     pub public: bool,
     pub name: String,
     pub type_ann: Option<String>,
@@ -27,12 +53,18 @@ pub struct ConstDecl {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryMap {
+    pub span: Span,
+
     pub regions: Vec<Region>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Region {
+    pub span: Span,
+
     pub name: String,
     pub permissions: Permissions,
     pub start: Expr,
@@ -40,6 +72,7 @@ pub struct Region {
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Permissions {
     pub read: bool,
     pub write: bool,
@@ -47,18 +80,25 @@ pub struct Permissions {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElfSegments {
+    pub span: Span,
+
     pub segments: Vec<Segment>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Segment {
+    pub span: Span,
+
     pub name: String,
     pub segment_type: SegmentType,
     pub permissions: Permissions,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SegmentType {
     Load,
     Dynamic,
@@ -70,7 +110,10 @@ pub enum SegmentType {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Section {
+    pub span: Span,
+
     pub name: String,
     pub place_in: Option<String>,
     pub load_from: Option<String>,
@@ -85,7 +128,10 @@ pub struct Section {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AddressBlock {
+    pub span: Span,
+
     pub start: Option<Expr>,
     pub size: Option<Expr>,
     pub alignment: Option<Expr>,
@@ -96,22 +142,28 @@ pub struct AddressBlock {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FilePosition {
     pub start: FilePositionStart,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilePositionStart {
     Origin,
     Expr(Expr),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Contents {
+    pub span: Span,
+
     pub items: Vec<ContentsItem>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ContentsItem {
     Symbol(SymbolDef),
     Input(InputStmt),
@@ -126,31 +178,40 @@ pub enum ContentsItem {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SymbolDef {
+    pub span: Span,
+
     pub public: bool,
     pub name: String,
     pub value: LocationExpr,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocationExpr {
     pub accessor: Option<LocationAccessor>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocationAccessor {
     Physical,
     Virtual,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputStmt {
+    pub span: Span,
+
     pub from: Option<String>, // glob pattern for file filter
     pub patterns: Vec<String>,
     pub sort_by: Option<SortKey>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SortKey {
     Name,
     Address,
@@ -158,6 +219,7 @@ pub enum SortKey {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CfgPredicate {
     Feature(String),
     Not(Box<CfgPredicate>),
@@ -166,22 +228,32 @@ pub enum CfgPredicate {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Assertion {
+    pub span: Span,
+
     pub condition: Expr,
     pub message: String,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Discard {
+    pub span: Span,
+
     pub patterns: Vec<InputStmt>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProvideSymbols {
+    pub span: Span,
+
     pub symbols: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Number(u64),
     Ident(String),
@@ -204,6 +276,7 @@ pub enum Expr {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinOp {
     Add,
     Sub,
@@ -0,0 +1,633 @@
+//! Lowering from the pest parse tree to the concrete AST.
+//!
+//! [`parse_file`] runs [`LinkrsParser`] over a source string and walks the
+//! resulting `Pairs` tree, constructing the [`Item`] types declared in the
+//! crate root. Expressions are handled separately by a [`PrattParser`] so that
+//! operator precedence and associativity live in one place.
+
+use {
+    crate::*,
+    pest::{
+        iterators::{Pair, Pairs},
+        pratt_parser::{Assoc, Op, PrattParser},
+        Parser,
+    },
+    std::{fmt, sync::OnceLock},
+};
+
+/// Error returned while turning source text into a `Vec<Item>`.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The input did not match the grammar.
+    Syntax(Box<pest::error::Error<Rule>>),
+    /// The input parsed but a rule carried an unexpected shape.
+    Malformed(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax(e) => write!(f, "{e}"),
+            ParseError::Malformed(m) => write!(f, "malformed input: {m}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<pest::error::Error<Rule>> for ParseError {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        ParseError::Syntax(Box::new(e))
+    }
+}
+
+/// Parse a complete linkrs source file into its AST.
+pub fn parse_file(input: &str) -> Result<Vec<Item>, ParseError> {
+    let mut file = LinkrsParser::parse(Rule::file, input)?;
+    let file = file.next().expect("file rule always yields one pair");
+    file.into_inner()
+        .filter(|p| p.as_rule() == Rule::item)
+        .map(lower_item)
+        .collect()
+}
+
+fn lower_item(pair: Pair<Rule>) -> Result<Item, ParseError> {
+    let inner = only(pair)?;
+    Ok(match inner.as_rule() {
+        Rule::const_decl => Item::Const(lower_const(inner)?),
+        Rule::memory_map => Item::MemoryMap(lower_memory_map(inner)?),
+        Rule::elf_segments => Item::ElfSegments(lower_elf_segments(inner)?),
+        Rule::section => Item::Section(Box::new(lower_section(inner)?)),
+        Rule::discard => Item::Discard(lower_discard(inner)?),
+        Rule::provide_symbols => Item::ProvideSymbols(lower_provide_symbols(inner)?),
+        other => return Err(unexpected("item", other)),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Constants
+// ---------------------------------------------------------------------------
+
+fn lower_const(pair: Pair<Rule>) -> Result<ConstDecl, ParseError> {
+    let span = pair.as_span().into();
+    let mut public = false;
+    let mut name = None;
+    let mut type_ann = None;
+    let mut value = None;
+    for p in pair.into_inner() {
+        match p.as_rule() {
+            Rule::pub_kw => public = true,
+            Rule::ident if name.is_none() => name = Some(p.as_str().to_owned()),
+            Rule::ident => type_ann = Some(p.as_str().to_owned()),
+            Rule::expr => value = Some(parse_expr(p)?),
+            other => return Err(unexpected("const_decl", other)),
+        }
+    }
+    Ok(ConstDecl {
+        span,
+        public,
+        name: name.ok_or_else(|| missing("const name"))?,
+        type_ann,
+        value: value.ok_or_else(|| missing("const value"))?,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Memory map
+// ---------------------------------------------------------------------------
+
+fn lower_memory_map(pair: Pair<Rule>) -> Result<MemoryMap, ParseError> {
+    let span = pair.as_span().into();
+    let regions = pair.into_inner().map(lower_region).collect::<Result<_, _>>()?;
+    Ok(MemoryMap { span, regions })
+}
+
+fn lower_region(pair: Pair<Rule>) -> Result<Region, ParseError> {
+    let span = pair.as_span().into();
+    let mut inner = pair.into_inner();
+    let name = inner.next().ok_or_else(|| missing("region name"))?.as_str().to_owned();
+    let mut permissions = Permissions::default();
+    let mut start = None;
+    let mut size = None;
+    for field in inner {
+        let f = only(field)?;
+        match f.as_rule() {
+            Rule::permissions => permissions = lower_permissions(f),
+            Rule::expr if start.is_none() => start = Some(parse_expr(f)?),
+            Rule::expr => size = Some(parse_expr(f)?),
+            other => return Err(unexpected("region_field", other)),
+        }
+    }
+    Ok(Region {
+        span,
+        name,
+        permissions,
+        start: start.ok_or_else(|| missing("region start"))?,
+        size: size.ok_or_else(|| missing("region size"))?,
+    })
+}
+
+fn lower_permissions(pair: Pair<Rule>) -> Permissions {
+    let mut perms = Permissions::default();
+    for p in pair.into_inner() {
+        match p.as_str() {
+            "Read" => perms.read = true,
+            "Write" => perms.write = true,
+            "Execute" => perms.execute = true,
+            _ => {}
+        }
+    }
+    perms
+}
+
+// ---------------------------------------------------------------------------
+// ELF segments
+// ---------------------------------------------------------------------------
+
+fn lower_elf_segments(pair: Pair<Rule>) -> Result<ElfSegments, ParseError> {
+    let span = pair.as_span().into();
+    let segments = pair.into_inner().map(lower_segment).collect::<Result<_, _>>()?;
+    Ok(ElfSegments { span, segments })
+}
+
+fn lower_segment(pair: Pair<Rule>) -> Result<Segment, ParseError> {
+    let span = pair.as_span().into();
+    let mut inner = pair.into_inner();
+    let name = inner.next().ok_or_else(|| missing("segment name"))?.as_str().to_owned();
+    let mut segment_type = None;
+    let mut permissions = Permissions::default();
+    for field in inner {
+        let f = only(field)?;
+        match f.as_rule() {
+            Rule::segment_type => segment_type = Some(lower_segment_type(&f)),
+            Rule::permissions => permissions = lower_permissions(f),
+            other => return Err(unexpected("segment_field", other)),
+        }
+    }
+    Ok(Segment {
+        span,
+        name,
+        segment_type: segment_type.ok_or_else(|| missing("segment type"))?,
+        permissions,
+    })
+}
+
+fn lower_segment_type(pair: &Pair<Rule>) -> SegmentType {
+    match pair.as_str() {
+        "Load" => SegmentType::Load,
+        "Dynamic" => SegmentType::Dynamic,
+        "Interp" => SegmentType::Interp,
+        "Note" => SegmentType::Note,
+        "Phdr" => SegmentType::Phdr,
+        "Tls" => SegmentType::Tls,
+        _ => SegmentType::Null,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Sections
+// ---------------------------------------------------------------------------
+
+fn lower_section(pair: Pair<Rule>) -> Result<Section, ParseError> {
+    let span = pair.as_span().into();
+    let mut inner = pair.into_inner();
+    let name = inner.next().ok_or_else(|| missing("section name"))?.as_str().to_owned();
+    let mut section = Section {
+        span,
+        name,
+        place_in: None,
+        load_from: None,
+        output_to: None,
+        permissions: None,
+        occupies_file_space: None,
+        address: None,
+        file_position: None,
+        contents: None,
+        assertions: Vec::new(),
+        no_cross_refs: Vec::new(),
+    };
+    for member in inner {
+        let m = only(member)?;
+        match m.as_rule() {
+            Rule::place_in => section.place_in = Some(field_ident(m)?),
+            Rule::load_from => section.load_from = Some(field_ident(m)?),
+            Rule::output_to => section.output_to = Some(field_ident(m)?),
+            Rule::sec_permissions => {
+                section.permissions = Some(lower_permissions(only(m)?))
+            }
+            Rule::occupies_file_space => {
+                section.occupies_file_space = Some(only(m)?.as_str() == "true")
+            }
+            Rule::address_block => section.address = Some(lower_address_block(m)?),
+            Rule::file_position => section.file_position = Some(lower_file_position(m)?),
+            Rule::contents_block => section.contents = Some(lower_contents(m)?),
+            Rule::assert_stmt => section.assertions.push(lower_assert(m)?),
+            Rule::no_cross_refs => {
+                section.no_cross_refs =
+                    m.into_inner().map(|p| p.as_str().to_owned()).collect()
+            }
+            other => return Err(unexpected("section_member", other)),
+        }
+    }
+    Ok(section)
+}
+
+fn lower_address_block(pair: Pair<Rule>) -> Result<AddressBlock, ParseError> {
+    let span = pair.as_span().into();
+    let mut block = AddressBlock {
+        span,
+        start: None,
+        size: None,
+        alignment: None,
+        follows: None,
+        virtual_base: None,
+        region: None,
+        load_from_region: None,
+    };
+    for field in pair.into_inner() {
+        // `address_field` names its key via the leading keyword text.
+        let key = field.as_str();
+        let value = only(field)?;
+        match value.as_rule() {
+            Rule::expr if key.starts_with("start") => block.start = Some(parse_expr(value)?),
+            Rule::expr if key.starts_with("size") => block.size = Some(parse_expr(value)?),
+            Rule::expr if key.starts_with("alignment") => {
+                block.alignment = Some(parse_expr(value)?)
+            }
+            Rule::expr if key.starts_with("virtual_base") => {
+                block.virtual_base = Some(parse_expr(value)?)
+            }
+            Rule::ident if key.starts_with("follows") => {
+                block.follows = Some(value.as_str().to_owned())
+            }
+            Rule::ident if key.starts_with("load_from_region") => {
+                block.load_from_region = Some(value.as_str().to_owned())
+            }
+            Rule::ident if key.starts_with("region") => {
+                block.region = Some(value.as_str().to_owned())
+            }
+            other => return Err(unexpected("address_field", other)),
+        }
+    }
+    Ok(block)
+}
+
+fn lower_file_position(pair: Pair<Rule>) -> Result<FilePosition, ParseError> {
+    let value = only(pair)?;
+    let start = match value.as_rule() {
+        Rule::file_position_start => match value.as_str() {
+            "Origin" => FilePositionStart::Origin,
+            _ => FilePositionStart::Expr(parse_expr(only(value)?)?),
+        },
+        other => return Err(unexpected("file_position", other)),
+    };
+    Ok(FilePosition { start })
+}
+
+fn lower_contents(pair: Pair<Rule>) -> Result<Contents, ParseError> {
+    let span = pair.as_span().into();
+    let items = pair.into_inner().map(lower_contents_item).collect::<Result<_, _>>()?;
+    Ok(Contents { span, items })
+}
+
+fn lower_contents_item(pair: Pair<Rule>) -> Result<ContentsItem, ParseError> {
+    let inner = only(pair)?;
+    Ok(match inner.as_rule() {
+        Rule::cfg_item => {
+            let mut it = inner.into_inner();
+            let predicate = lower_cfg_predicate(only(
+                it.next().ok_or_else(|| missing("cfg attr"))?,
+            )?)?;
+            let item = Box::new(lower_contents_item(
+                it.next().ok_or_else(|| missing("cfg item"))?,
+            )?);
+            ContentsItem::Cfg { predicate, item }
+        }
+        Rule::input_stmt => ContentsItem::Input(lower_input(inner)?),
+        Rule::keep_stmt => ContentsItem::Keep(lower_input(only(inner)?)?),
+        Rule::align_to => ContentsItem::AlignTo(parse_expr(only(inner)?)?),
+        Rule::advance_by => ContentsItem::AdvanceBy(parse_expr(only(inner)?)?),
+        Rule::fill_padding_with => {
+            ContentsItem::FillPaddingWith(parse_expr(only(inner)?)?)
+        }
+        Rule::symbol_def => ContentsItem::Symbol(lower_symbol(inner)?),
+        other => return Err(unexpected("contents_item", other)),
+    })
+}
+
+fn lower_cfg_predicate(pair: Pair<Rule>) -> Result<CfgPredicate, ParseError> {
+    Ok(match pair.as_rule() {
+        // `cfg_predicate` is a thin wrapper around the chosen variant.
+        Rule::cfg_predicate => lower_cfg_predicate(only(pair)?)?,
+        Rule::cfg_feature => CfgPredicate::Feature(unquote(only(pair)?.as_str())),
+        Rule::cfg_not => CfgPredicate::Not(Box::new(lower_cfg_predicate(only(pair)?)?)),
+        Rule::cfg_all => CfgPredicate::All(
+            pair.into_inner().map(lower_cfg_predicate).collect::<Result<_, _>>()?,
+        ),
+        Rule::cfg_any => CfgPredicate::Any(
+            pair.into_inner().map(lower_cfg_predicate).collect::<Result<_, _>>()?,
+        ),
+        other => return Err(unexpected("cfg_predicate", other)),
+    })
+}
+
+fn lower_input(pair: Pair<Rule>) -> Result<InputStmt, ParseError> {
+    let span = pair.as_span().into();
+    let mut patterns = Vec::new();
+    let mut sort_by = None;
+    for p in pair.into_inner() {
+        match p.as_rule() {
+            Rule::glob => patterns.push(p.as_str().to_owned()),
+            Rule::sort_by => sort_by = Some(lower_sort_key(only(p)?)),
+            other => return Err(unexpected("input_stmt", other)),
+        }
+    }
+    Ok(InputStmt { span, from: None, patterns, sort_by })
+}
+
+fn lower_sort_key(pair: Pair<Rule>) -> SortKey {
+    match pair.as_str() {
+        "Name" => SortKey::Name,
+        "Address" => SortKey::Address,
+        _ => SortKey::Alignment,
+    }
+}
+
+fn lower_symbol(pair: Pair<Rule>) -> Result<SymbolDef, ParseError> {
+    let span = pair.as_span().into();
+    let mut public = false;
+    let mut name = None;
+    let mut accessor = None;
+    for p in pair.into_inner() {
+        match p.as_rule() {
+            Rule::pub_kw => public = true,
+            Rule::ident => name = Some(p.as_str().to_owned()),
+            Rule::location_expr => {
+                accessor = p.into_inner().next().map(|a| match a.as_str() {
+                    "virtual" => LocationAccessor::Virtual,
+                    _ => LocationAccessor::Physical,
+                })
+            }
+            other => return Err(unexpected("symbol_def", other)),
+        }
+    }
+    Ok(SymbolDef {
+        span,
+        public,
+        name: name.ok_or_else(|| missing("symbol name"))?,
+        value: LocationExpr { accessor },
+    })
+}
+
+fn lower_assert(pair: Pair<Rule>) -> Result<Assertion, ParseError> {
+    let span = pair.as_span().into();
+    let mut inner = pair.into_inner();
+    let condition = parse_expr(inner.next().ok_or_else(|| missing("assert condition"))?)?;
+    let message = unquote(inner.next().ok_or_else(|| missing("assert message"))?.as_str());
+    Ok(Assertion { span, condition, message })
+}
+
+// ---------------------------------------------------------------------------
+// Discard & provide_symbols
+// ---------------------------------------------------------------------------
+
+fn lower_discard(pair: Pair<Rule>) -> Result<Discard, ParseError> {
+    let span = pair.as_span().into();
+    let patterns = pair.into_inner().map(lower_input).collect::<Result<_, _>>()?;
+    Ok(Discard { span, patterns })
+}
+
+fn lower_provide_symbols(pair: Pair<Rule>) -> Result<ProvideSymbols, ParseError> {
+    let span = pair.as_span().into();
+    let mut symbols = Vec::new();
+    for pair_rule in pair.into_inner() {
+        let mut kv = pair_rule.into_inner();
+        let key = kv.next().ok_or_else(|| missing("provide key"))?.as_str().to_owned();
+        let val = kv.next().ok_or_else(|| missing("provide value"))?.as_str().to_owned();
+        symbols.push((key, val));
+    }
+    Ok(ProvideSymbols { span, symbols })
+}
+
+// ---------------------------------------------------------------------------
+// Expressions
+// ---------------------------------------------------------------------------
+
+fn pratt() -> &'static PrattParser<Rule> {
+    static PRATT: OnceLock<PrattParser<Rule>> = OnceLock::new();
+    PRATT.get_or_init(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::lt, Assoc::Left)
+                | Op::infix(Rule::gt, Assoc::Left)
+                | Op::infix(Rule::le, Assoc::Left)
+                | Op::infix(Rule::ge, Assoc::Left)
+                | Op::infix(Rule::eq, Assoc::Left)
+                | Op::infix(Rule::ne, Assoc::Left))
+            .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
+            .op(Op::infix(Rule::mul, Assoc::Left)
+                | Op::infix(Rule::div, Assoc::Left)
+                | Op::infix(Rule::modulo, Assoc::Left))
+            .op(Op::prefix(Rule::neg))
+            .op(Op::postfix(Rule::member) | Op::postfix(Rule::call))
+    })
+}
+
+/// Lower a `Rule::expr` pair into an [`Expr`] via the shared Pratt parser.
+pub fn parse_expr(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+    parse_expr_inner(pair.into_inner())
+}
+
+fn parse_expr_inner(pairs: Pairs<Rule>) -> Result<Expr, ParseError> {
+    pratt()
+        .map_primary(map_primary)
+        .map_prefix(|op, rhs| {
+            let rhs = rhs?;
+            match op.as_rule() {
+                Rule::neg => Ok(Expr::UnaryMinus(Box::new(rhs))),
+                other => Err(unexpected("prefix", other)),
+            }
+        })
+        .map_postfix(|lhs, op| {
+            let lhs = lhs?;
+            match op.as_rule() {
+                Rule::member => {
+                    let field = only(op)?.as_str().to_owned();
+                    Ok(Expr::Member { expr: Box::new(lhs), field })
+                }
+                Rule::call => {
+                    let args = op
+                        .into_inner()
+                        .map(parse_expr)
+                        .collect::<Result<_, _>>()?;
+                    Ok(Expr::Call { func: Box::new(lhs), args })
+                }
+                other => Err(unexpected("postfix", other)),
+            }
+        })
+        .map_infix(|lhs, op, rhs| {
+            let (lhs, rhs) = (lhs?, rhs?);
+            let op = match op.as_rule() {
+                Rule::add => BinOp::Add,
+                Rule::sub => BinOp::Sub,
+                Rule::mul => BinOp::Mul,
+                Rule::div => BinOp::Div,
+                Rule::modulo => BinOp::Mod,
+                Rule::lt => BinOp::Lt,
+                Rule::gt => BinOp::Gt,
+                Rule::le => BinOp::Le,
+                Rule::ge => BinOp::Ge,
+                Rule::eq => BinOp::Eq,
+                Rule::ne => BinOp::Ne,
+                other => return Err(unexpected("infix", other)),
+            };
+            Ok(Expr::BinOp { left: Box::new(lhs), op, right: Box::new(rhs) })
+        })
+        .parse(pairs)
+}
+
+fn map_primary(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+    match pair.as_rule() {
+        Rule::number => Ok(Expr::Number(parse_number(pair.as_str())?)),
+        Rule::ident => Ok(Expr::Ident(pair.as_str().to_owned())),
+        Rule::here => Ok(Expr::Here),
+        Rule::size => Ok(Expr::Size),
+        Rule::expr => parse_expr(pair),
+        other => Err(unexpected("primary", other)),
+    }
+}
+
+/// Parse a numeric literal, honoring `0x` hex, `_` separators and `K`/`M`/`G`
+/// binary size suffixes.
+fn parse_number(raw: &str) -> Result<u64, ParseError> {
+    let cleaned: String = raw.chars().filter(|c| *c != '_').collect();
+    if let Some(hex) = cleaned.strip_prefix("0x").or_else(|| cleaned.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16)
+            .map_err(|e| ParseError::Malformed(format!("hex literal `{raw}`: {e}")));
+    }
+    let (digits, multiplier) = match cleaned.as_bytes().last() {
+        Some(b'K') => (&cleaned[..cleaned.len() - 1], 1024),
+        Some(b'M') => (&cleaned[..cleaned.len() - 1], 1024 * 1024),
+        Some(b'G') => (&cleaned[..cleaned.len() - 1], 1024 * 1024 * 1024),
+        _ => (cleaned.as_str(), 1),
+    };
+    let base = digits
+        .parse::<u64>()
+        .map_err(|e| ParseError::Malformed(format!("literal `{raw}`: {e}")))?;
+    base.checked_mul(multiplier)
+        .ok_or_else(|| ParseError::Malformed(format!("literal `{raw}` overflows u64")))
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Unwrap a rule that always carries exactly one child.
+fn only(pair: Pair<Rule>) -> Result<Pair<Rule>, ParseError> {
+    pair.into_inner().next().ok_or_else(|| missing("nested rule"))
+}
+
+fn field_ident(pair: Pair<Rule>) -> Result<String, ParseError> {
+    Ok(only(pair)?.as_str().to_owned())
+}
+
+fn unquote(s: &str) -> String {
+    s.trim_matches('"').to_owned()
+}
+
+fn missing(what: &str) -> ParseError {
+    ParseError::Malformed(format!("missing {what}"))
+}
+
+fn unexpected(ctx: &str, rule: Rule) -> ParseError {
+    ParseError::Malformed(format!("unexpected {rule:?} in {ctx}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_full_embedded() {
+        let input = r#"
+memory_map {
+    region FLASH {
+        permissions: Read | Execute,
+        start: 0x0800_0000,
+        size: 256K,
+    }
+}
+
+section .text {
+    place_in: FLASH,
+    output_to: segment(flash),
+
+    contents {
+        input(.text*)
+    }
+}
+"#
+        .trim();
+        let items = parse_file(input).expect("should lower");
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            Item::MemoryMap(m) => {
+                assert_eq!(m.regions[0].name, "FLASH");
+                assert!(m.regions[0].permissions.read);
+                assert!(m.regions[0].permissions.execute);
+            }
+            other => panic!("expected memory_map, got {other:?}"),
+        }
+        match &items[1] {
+            Item::Section(s) => {
+                assert_eq!(s.name, ".text");
+                assert_eq!(s.place_in.as_deref(), Some("FLASH"));
+                assert_eq!(s.output_to.as_deref(), Some("flash"));
+            }
+            other => panic!("expected section, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_number_suffixes() {
+        assert_eq!(parse_number("256K").unwrap(), 256 * 1024);
+        assert_eq!(parse_number("0x0800_0000").unwrap(), 0x0800_0000);
+        assert_eq!(parse_number("1_000").unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_lower_cfg_predicate() {
+        let src = r#"
+section .text {
+    contents {
+        #[cfg(all(feature = "a", not(feature = "b")))]
+        input(.text*)
+    }
+}
+"#
+        .trim();
+        let mut items = parse_file(src).unwrap();
+        let Item::Section(section) = items.remove(0) else { panic!("expected section") };
+        let contents = section.contents.expect("contents");
+        match &contents.items[0] {
+            ContentsItem::Cfg { predicate: CfgPredicate::All(preds), item } => {
+                assert_eq!(preds.len(), 2);
+                assert!(matches!(preds[0], CfgPredicate::Feature(ref f) if f == "a"));
+                assert!(matches!(preds[1], CfgPredicate::Not(_)));
+                assert!(matches!(**item, ContentsItem::Input(_)));
+            }
+            other => panic!("expected cfg guard, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_expr_precedence() {
+        let decl = "const X: usize = 1 + 2 * 3 < 10;";
+        let mut items = parse_file(decl).unwrap();
+        let Item::Const(c) = items.remove(0) else { panic!("expected const") };
+        // `<` binds loosest, so the top node is a comparison.
+        match c.value {
+            Expr::BinOp { op: BinOp::Lt, .. } => {}
+            other => panic!("expected top-level Lt, got {other:?}"),
+        }
+    }
+}
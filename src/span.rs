@@ -0,0 +1,33 @@
+//! Source spans carried by AST nodes.
+//!
+//! A [`Span`] is a half-open byte range into the original source string,
+//! mirroring the offsets that pest exposes via [`pest::Span`]. Diagnostics use
+//! these ranges to underline the offending text.
+
+use std::ops::Range;
+
+/// A half-open `[start, end)` byte range into the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Construct a span from explicit byte offsets.
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The span as a `Range`, as wanted by `codespan-reporting` labels.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Span::new(span.start(), span.end())
+    }
+}